@@ -1,26 +1,93 @@
-use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
-use std::{mem, ptr};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-/// Convert any reference into any other.
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut, Index, IndexMut};
+use core::pin::Pin;
+use core::{mem, ptr, slice};
+
+/// Convert any reference into any other of the same size and alignment.
 #[inline]
 unsafe fn transmute_ref<FromT, ToT>(from: &FromT) -> &ToT {
-    debug_assert_eq!(mem::size_of::<FromT>(), mem::size_of::<ToT>());
+    const {
+        assert!(mem::size_of::<FromT>() == mem::size_of::<ToT>());
+        assert!(mem::align_of::<FromT>() == mem::align_of::<ToT>());
+    };
     &*(from as *const FromT as *const ToT)
 }
 
-/// Convert any mutable reference into any other.
+/// Convert any mutable reference into any other of the same size and alignment.
 #[inline]
 pub(crate) unsafe fn transmute_ref_mut<FromT, ToT>(from: &mut FromT) -> &mut ToT {
-    debug_assert_eq!(mem::size_of::<FromT>(), mem::size_of::<ToT>());
+    const {
+        assert!(mem::size_of::<FromT>() == mem::size_of::<ToT>());
+        assert!(mem::align_of::<FromT>() == mem::align_of::<ToT>());
+    };
     &mut *(from as *mut FromT as *mut ToT)
 }
 
-pub struct Handle<T>(
-    T,
-    // `*const` is needed to prevent automatic Send and Sync derivation if T implements Send and Sync.
-    PhantomData<*const ()>,
-);
+/// Convert any slice into a slice of any other type of the same size and alignment.
+#[inline]
+unsafe fn transmute_slice<FromT, ToT>(from: &[FromT]) -> &[ToT] {
+    const {
+        assert!(mem::size_of::<FromT>() == mem::size_of::<ToT>());
+        assert!(mem::align_of::<FromT>() == mem::align_of::<ToT>());
+    };
+    slice::from_raw_parts(from.as_ptr() as *const ToT, from.len())
+}
+
+/// Convert any mutable slice into a mutable slice of any other type of the same size and alignment.
+#[inline]
+unsafe fn transmute_slice_mut<FromT, ToT>(from: &mut [FromT]) -> &mut [ToT] {
+    const {
+        assert!(mem::size_of::<FromT>() == mem::size_of::<ToT>());
+        assert!(mem::align_of::<FromT>() == mem::align_of::<ToT>());
+    };
+    slice::from_raw_parts_mut(from.as_mut_ptr() as *mut ToT, from.len())
+}
+
+/// Convert any vector into a vector of any other type of the same size and alignment, without
+/// reallocating.
+#[cfg(feature = "alloc")]
+#[inline]
+unsafe fn transmute_vec<FromT, ToT>(from: Vec<FromT>) -> Vec<ToT> {
+    const {
+        assert!(mem::size_of::<FromT>() == mem::size_of::<ToT>());
+        assert!(mem::align_of::<FromT>() == mem::align_of::<ToT>());
+    };
+    let mut from = mem::ManuallyDrop::new(from);
+    Vec::from_raw_parts(from.as_mut_ptr() as *mut ToT, from.len(), from.capacity())
+}
+
+// `*const` is needed to prevent automatic Send and Sync derivation if T implements Send and Sync.
+#[cfg(not(feature = "auto_traits"))]
+type HandleMarker = PhantomData<*const ()>;
+
+// Send and Sync are derived from T.
+#[cfg(feature = "auto_traits")]
+type HandleMarker = PhantomData<()>;
+
+#[repr(transparent)]
+pub struct Handle<T>(T, HandleMarker);
+
+/// Marks a type as having the same layout as `U`, so that a [`Handle`] to it can be
+/// reinterpreted as a handle to `U` with [`Handle::cast_ref`]. Implementing it in both directions
+/// also enables [`Handle::cast_mut`].
+///
+/// # Safety
+///
+/// The type must have the same size and alignment as `U`, and every valid value of the type must
+/// also be a valid value of `U`. This usually means both are `#[repr(C)]` with identical fields.
+pub unsafe trait LayoutCompatible<U> {}
 
 impl<T> AsRef<Handle<T>> for Handle<T> {
     #[inline]
@@ -29,6 +96,34 @@ impl<T> AsRef<Handle<T>> for Handle<T> {
     }
 }
 
+impl<T> AsRef<T> for Handle<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self.instance()
+    }
+}
+
+impl<T> AsMut<T> for Handle<T> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut T {
+        self.instance_mut()
+    }
+}
+
+impl<T> Borrow<T> for Handle<T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        self.instance()
+    }
+}
+
+impl<T> BorrowMut<T> for Handle<T> {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut T {
+        self.instance_mut()
+    }
+}
+
 impl<T> From<T> for Handle<T> {
     fn from(t: T) -> Self {
         Self::from_instance(t)
@@ -39,10 +134,20 @@ impl<T> Handle<T> {
     /// Wrap a struct instance into a handle.
     #[inline]
     #[must_use]
-    pub fn from_instance(t: T) -> Self {
+    pub const fn from_instance(t: T) -> Self {
         Handle(t, PhantomData)
     }
 
+    /// Wrap a struct instance into a pinned handle, so that the instance is never moved again.
+    ///
+    /// Mutable access to the instance through the pin is only possible if `T` is [`Unpin`].
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn pin(t: T) -> Pin<Box<Self>> {
+        Box::pin(Self::from_instance(t))
+    }
+
     /// Wrap a struct reference into a handle.
     #[inline]
     #[must_use]
@@ -57,6 +162,28 @@ impl<T> Handle<T> {
         unsafe { transmute_ref_mut(t) }
     }
 
+    /// Wrap a slice of struct instances into a slice of handles.
+    #[inline]
+    #[must_use]
+    pub fn from_slice(s: &[T]) -> &[Self] {
+        unsafe { transmute_slice(s) }
+    }
+
+    /// Wrap a mutable slice of struct instances into a mutable slice of handles.
+    #[inline]
+    #[must_use]
+    pub fn from_slice_mut(s: &mut [T]) -> &mut [Self] {
+        unsafe { transmute_slice_mut(s) }
+    }
+
+    /// Wrap a vector of struct instances into a vector of handles, without reallocating.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn from_vec(v: Vec<T>) -> Vec<Self> {
+        unsafe { transmute_vec(v) }
+    }
+
     /// Wrap a const pointer into a const handle pointer.
     #[inline]
     #[must_use]
@@ -80,13 +207,109 @@ impl<T> Handle<T> {
         self
     }
 
+    /// Replaces the instance with the result of `f` applied to it.
+    ///
+    /// The process is aborted if `f` panics, as the handle would otherwise be left without an
+    /// instance.
+    #[inline]
+    pub fn replace_with(&mut self, f: impl FnOnce(T) -> T) {
+        struct AbortOnUnwind;
+
+        impl Drop for AbortOnUnwind {
+            fn drop(&mut self) {
+                // panicking while unwinding aborts the process
+                panic!("`replace_with` closure panicked");
+            }
+        }
+
+        let guard = AbortOnUnwind;
+        unsafe {
+            let t = ptr::read(&self.0);
+            ptr::write(&mut self.0, f(t));
+        }
+        mem::forget(guard);
+    }
+
+    /// Swaps the instances of two handles without dropping either one.
+    #[inline]
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(&mut self.0, &mut other.0);
+    }
+
+    /// Replaces the instance with its default value, and returns the replaced one.
+    #[inline]
+    #[must_use]
+    pub fn take(&mut self) -> T
+    where
+        T: Default,
+    {
+        mem::take(&mut self.0)
+    }
+
     /// Consumes the wrapper and returns the wrapped type.
     #[inline]
     #[must_use]
-    pub fn into_instance(mut self) -> T {
-        let r = mem::replace(&mut self.0, unsafe { mem::zeroed() });
-        mem::forget(self);
-        r
+    pub fn into_instance(self) -> T {
+        let this = mem::ManuallyDrop::new(self);
+        // `this` is never dropped, so the instance is moved out exactly once
+        unsafe { ptr::read(&this.0) }
+    }
+
+    /// Consumes the wrapper and returns a new one wrapping the result of `f` applied to the
+    /// wrapped type.
+    #[inline]
+    #[must_use]
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Handle<U> {
+        Handle::from_instance(f(self.into_instance()))
+    }
+
+    /// Consumes the wrapper and returns a new one wrapping the result of `f` applied to the
+    /// wrapped type, or the error returned by `f`.
+    ///
+    /// The wrapped type is moved into `f`, so it is not returned on error.
+    #[inline]
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Handle<U>, E> {
+        f(self.into_instance()).map(Handle::from_instance)
+    }
+
+    /// Consumes the wrapper, moves the wrapped type to the heap and returns an owning pointer to
+    /// it.
+    ///
+    /// The wrapped type is not dropped, use [`from_owned_ptr`](Handle::from_owned_ptr) to reclaim
+    /// it.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn into_ptr(self) -> *mut T {
+        Box::into_raw(Box::new(self.into_instance()))
+    }
+
+    /// Consumes the wrapper, moves the wrapped type to the heap and returns a mutable reference
+    /// to it that lives for the rest of the program.
+    ///
+    /// The wrapped type is leaked and never dropped.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn leak<'a>(self) -> &'a mut T
+    where
+        T: 'a,
+    {
+        Box::leak(Box::new(self.into_instance()))
+    }
+
+    /// Reclaims a pointer returned by [`into_ptr`](Handle::into_ptr) and wraps the pointed to
+    /// instance into a handle.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have been returned by [`into_ptr`](Handle::into_ptr) and must not be
+    /// used after this call.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub unsafe fn from_owned_ptr(ptr: *mut T) -> Self {
+        Self::from_instance(*Box::from_raw(ptr))
     }
 
     /// Returns a reference to the wrapped type.
@@ -102,210 +325,2375 @@ impl<T> Handle<T> {
     pub fn instance_mut(&mut self) -> &mut T {
         &mut self.0
     }
-}
 
-impl<T: Clone> Clone for Handle<T> {
-    fn clone(&self) -> Self {
-        Self::from_instance(self.0.clone())
+    /// Returns a slice of the types wrapped by a slice of handles.
+    #[inline]
+    #[must_use]
+    pub fn as_slice(handles: &[Self]) -> &[T] {
+        unsafe { transmute_slice(handles) }
     }
-}
 
-impl<T: PartialEq> PartialEq for Handle<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.instance().eq(other.instance())
+    /// Consumes a vector of handles and returns a vector of the wrapped types, without
+    /// reallocating.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    #[must_use]
+    pub fn into_vec(handles: Vec<Self>) -> Vec<T> {
+        unsafe { transmute_vec(handles) }
     }
-}
 
-impl<T> Deref for Handle<T> {
-    type Target = T;
+    /// Returns a pinned reference to the wrapped type.
+    #[inline]
+    #[must_use]
+    pub fn as_pin(self: Pin<&Self>) -> Pin<&T> {
+        // the instance is pinned because the handle is
+        unsafe { self.map_unchecked(|handle| &handle.0) }
+    }
 
+    /// Returns a pinned mutable reference to the wrapped type.
     #[inline]
-    fn deref(&self) -> &Self::Target {
+    #[must_use]
+    pub fn as_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+        // the instance is pinned because the handle is
+        unsafe { self.map_unchecked_mut(|handle| &mut handle.0) }
+    }
+
+    /// Returns a reference to the wrapped native type, same as [`instance`](Handle::instance).
+    #[inline]
+    #[must_use]
+    pub fn native(&self) -> &T {
         self.instance()
     }
-}
 
-impl<T> DerefMut for Handle<T> {
+    /// Returns a mutable reference to the wrapped native type, same as
+    /// [`instance_mut`](Handle::instance_mut).
     #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
+    #[must_use]
+    pub fn native_mut(&mut self) -> &mut T {
         self.instance_mut()
     }
-}
-
-#[cfg(feature = "send_sync")]
-unsafe impl<T> Send for Handle<T> {}
-
-#[cfg(feature = "send_sync")]
-unsafe impl<T> Sync for Handle<T> {}
-
-/// A wrapper type represented by a reference counted pointer to the wrapped type.
-#[repr(transparent)]
-pub struct RCHandle<T>(ptr::NonNull<T>);
 
-impl<T> From<&RCHandle<T>> for RCHandle<T> {
-    fn from(rch: &RCHandle<T>) -> Self {
-        rch.clone().into()
+    /// Returns a shared reference to the handle with a shorter lifetime, leaving the original
+    /// reference usable once it ends.
+    #[inline]
+    #[must_use]
+    pub fn reborrow(&self) -> &Handle<T> {
+        self
     }
-}
 
-impl<T> AsRef<RCHandle<T>> for RCHandle<T> {
-    fn as_ref(&self) -> &RCHandle<T> {
+    /// Returns a mutable reference to the handle with a shorter lifetime, leaving the original
+    /// reference usable once it ends.
+    #[inline]
+    #[must_use]
+    pub fn reborrow_mut(&mut self) -> &mut Handle<T> {
         self
     }
-}
 
-impl<T> RCHandle<T> {
-    /// Create a reference counted handle from a pointer.
+    /// Reinterpret the handle as a handle to a layout compatible type.
     ///
-    /// Takes ownership of the object the pointer points to, does not increase the reference count.
+    /// The size and alignment of both types are checked at compile time, so a wrong
+    /// [`LayoutCompatible`] impl between differently sized types fails to build:
     ///
-    /// Returns [`None`] if the pointer is `null`.
+    /// ```compile_fail,E0080
+    /// use type_handle::{Handle, LayoutCompatible};
+    ///
+    /// struct A(u8);
+    /// struct B(u64);
+    ///
+    /// unsafe impl LayoutCompatible<B> for A {}
+    ///
+    /// let handle = Handle::from_instance(A(1));
+    /// let _: &Handle<B> = handle.cast_ref();
+    /// ```
     #[inline]
-    pub fn from_ptr(ptr: *mut T) -> Option<Self> {
-        ptr::NonNull::new(ptr).map(Self)
+    #[must_use]
+    pub fn cast_ref<U>(&self) -> &Handle<U>
+    where
+        T: LayoutCompatible<U>,
+    {
+        unsafe { transmute_ref(self) }
     }
 
-    /// Create a reference counted handle from a pointer.
-    ///
-    /// Shares ownership with the object the pointer points to, therefore increases the reference count.
+    /// Reinterpret the handle as a mutable handle to a layout compatible type.
     ///
-    /// Returns [`None`] if the pointer is `null`.
+    /// Both types must be compatible with each other, since values of `U` can be written back.
     #[inline]
-    pub fn from_unshared_ptr(ptr: *mut T) -> Option<Self> {
-        ptr::NonNull::new(ptr).map(|ptr| {
-            unsafe {
-                let _ = ptr.as_ref();
-            }
-            Self(ptr)
-        })
+    #[must_use]
+    pub fn cast_mut<U>(&mut self) -> &mut Handle<U>
+    where
+        T: LayoutCompatible<U>,
+        U: LayoutCompatible<T>,
+    {
+        unsafe { transmute_ref_mut(self) }
     }
 
-    /// Create a reference to the wrapper from a reference to a pointer that points to the wrapped type.
+    /// Returns a const pointer to the wrapped type.
     #[inline]
-    pub fn from_unshared_ptr_ref(t: &*mut T) -> &Option<Self> {
-        unsafe { transmute_ref(t) }
+    #[must_use]
+    pub fn as_ptr(&self) -> *const T {
+        &self.0
     }
 
-    /// Create a reference counted handle from a mutable reference.
-    ///
-    /// Takes ownership of the referenced object.
+    /// Returns a mut pointer to the wrapped type.
     #[inline]
-    pub fn from_ref(t: &mut T) -> Self {
-        // references cannot be null, so it's safe to call unwrap_unchecked() here
-        unsafe { Self::from_ptr(t).unwrap_unchecked() }
+    #[must_use]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        &mut self.0
     }
 
-    /// Returns the pointer to the handle.
+    /// Creates a handle with uninitialized contents, to be filled in by the native side.
     #[inline]
-    pub fn as_ptr(&self) -> &ptr::NonNull<T> {
-        &self.0
+    #[must_use]
+    pub const fn uninit() -> Handle<mem::MaybeUninit<T>> {
+        Handle::from_instance(mem::MaybeUninit::uninit())
     }
+}
 
-    /// Returns a reference to the wrapped type.
+impl<T> Handle<mem::MaybeUninit<T>> {
+    /// Initializes the handle with `value` and returns the initialized handle.
+    ///
+    /// Any value previously written through [`as_write_ptr`](Handle::as_write_ptr) is
+    /// overwritten without being dropped.
     #[inline]
-    pub fn as_ref(&self) -> &T {
-        unsafe { self.0.as_ref() }
+    #[must_use]
+    pub fn write(mut self, value: T) -> Handle<T> {
+        self.0.write(value);
+        // just initialized above
+        unsafe { self.assume_init() }
     }
 
-    /// Returns a mutable reference to the wrapped type.
+    /// Converts to an initialized handle.
+    ///
+    /// # Safety
+    ///
+    /// The contents must have been fully initialized, by Rust or by the native side.
     #[inline]
-    pub fn as_mut(&mut self) -> &mut T {
-        unsafe { self.0.as_mut() }
+    #[must_use]
+    pub unsafe fn assume_init(self) -> Handle<T> {
+        Handle::from_instance(self.into_instance().assume_init())
     }
 
-    /// Consumes the wrapper and returns a pointer to the wrapped type.
+    /// Returns a mut pointer to the uninitialized type, for a native init function to write
+    /// into.
+    ///
+    /// Nothing may be read through the pointer before it has been fully initialized.
     #[inline]
-    pub fn into_ptr(self) -> *mut T {
-        let ptr = self.0.as_ptr();
-        mem::forget(self);
-        ptr
+    #[must_use]
+    pub fn as_write_ptr(&mut self) -> *mut T {
+        self.0.as_mut_ptr()
     }
 }
 
-impl<T> Clone for RCHandle<T> {
-    fn clone(&self) -> Self {
-        let ptr = self.0;
-        unsafe {
-            let _ = ptr.as_ref();
-        }
-        Self(ptr)
+impl<T: Default> Default for Handle<T> {
+    fn default() -> Self {
+        Self::from_instance(T::default())
     }
 }
 
-impl<T: PartialEq> PartialEq for RCHandle<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.as_ref().eq(other.as_ref())
+impl<T: Clone> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self::from_instance(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.instance_mut().clone_from(source.instance())
     }
 }
 
-impl<T> Deref for RCHandle<T> {
-    type Target = T;
+/// A type that can be duplicated natively, for example by calling a C copy constructor.
+pub trait NativeClone {
+    /// Returns a copy of the object.
+    fn clone(&self) -> Self;
+}
 
+impl<T: NativeClone> Handle<T> {
+    /// Returns a handle wrapping a native copy of the wrapped type.
     #[inline]
-    fn deref(&self) -> &Self::Target {
-        self.as_ref()
+    #[must_use]
+    pub fn clone_native(&self) -> Self {
+        Self::from_instance(NativeClone::clone(self.instance()))
+    }
+}
+
+impl<T: PartialEq> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.instance().eq(other.instance())
+    }
+}
+
+impl<T: PartialEq> PartialEq<T> for Handle<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.instance().eq(other)
     }
 }
 
-impl<T> DerefMut for RCHandle<T> {
+/// A type that can be compared natively, for example by calling a C comparison function.
+pub trait NativePartialEq {
+    /// Returns `true` if both objects are equal.
+    fn eq(&self, other: &Self) -> bool;
+}
+
+impl<T: NativePartialEq> Handle<T> {
+    /// Returns `true` if the wrapped types are natively equal.
     #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.as_mut()
+    #[must_use]
+    pub fn eq_native(&self, other: &Self) -> bool {
+        NativePartialEq::eq(self.instance(), other.instance())
     }
 }
 
-#[cfg(feature = "send_sync")]
-unsafe impl<T> Send for RCHandle<T> {}
+impl<T: Eq> Eq for Handle<T> {}
 
-#[cfg(feature = "send_sync")]
-unsafe impl<T> Sync for RCHandle<T> {}
+impl<T: PartialOrd> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.instance().partial_cmp(other.instance())
+    }
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<T: Ord> Ord for Handle<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.instance().cmp(other.instance())
+    }
+}
 
-    struct Thing {
-        number: i32,
+impl<T: Hash> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.instance().hash(state)
     }
+}
 
-    #[test]
-    fn test_handle() {
-        for num in 0..128 {
-            let thing = Thing { number: num };
-            let handle = Handle::from_instance(thing);
-            assert!(handle.number == num && handle.instance().number == num);
-        }
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Handle<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.instance().serialize(serializer)
     }
+}
 
-    #[test]
-    fn test_handle_mut() {
-        for num in 0..128 {
-            let thing = Thing { number: num };
-            let mut handle = Handle::from_instance(thing);
-            assert!(
-                handle.number == num
-                    && handle.instance().number == num
-                    && handle.instance_mut().number == num
-            );
-            let new_num = num * 5;
-            handle.number = new_num;
-            assert!(handle.number == new_num);
-        }
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Handle<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::from_instance)
     }
+}
 
-    #[test]
-    fn test_rchandle() {
-        for num in 0..128 {
-            let mut thing = Thing { number: num };
-            let mut rch = RCHandle::from_ptr(&mut thing).unwrap();
+impl<T: Index<I>, I> Index<I> for Handle<T> {
+    type Output = T::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        self.instance().index(index)
+    }
+}
+
+impl<T: IndexMut<I>, I> IndexMut<I> for Handle<T> {
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        self.instance_mut().index_mut(index)
+    }
+}
+
+impl<T: IntoIterator> IntoIterator for Handle<T> {
+    type Item = T::Item;
+    type IntoIter = T::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_instance().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Handle<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instance().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Handle<T>
+where
+    &'a mut T: IntoIterator,
+{
+    type Item = <&'a mut T as IntoIterator>::Item;
+    type IntoIter = <&'a mut T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instance_mut().into_iter()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(self.instance()).finish()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.instance(), f)
+    }
+}
+
+impl<T: fmt::LowerHex> fmt::LowerHex for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self.instance(), f)
+    }
+}
+
+impl<T: fmt::UpperHex> fmt::UpperHex for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::UpperHex::fmt(self.instance(), f)
+    }
+}
+
+impl<T: fmt::Binary> fmt::Binary for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Binary::fmt(self.instance(), f)
+    }
+}
+
+impl<T: fmt::Octal> fmt::Octal for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Octal::fmt(self.instance(), f)
+    }
+}
+
+impl<T: fmt::Pointer> fmt::Pointer for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(self.instance(), f)
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.instance()
+    }
+}
+
+impl<T> DerefMut for Handle<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.instance_mut()
+    }
+}
+
+#[cfg(feature = "send")]
+unsafe impl<T> Send for Handle<T> {}
+
+#[cfg(feature = "sync")]
+unsafe impl<T> Sync for Handle<T> {}
+
+/// The error returned when trying to create a handle from a `null` pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullPointerError;
+
+impl fmt::Display for NullPointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("pointer is null")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NullPointerError {}
+
+/// A type that manages its own (native) reference count.
+///
+/// # Safety
+///
+/// Implementors must guarantee that the object stays alive for as long as its reference count
+/// is above zero, and that [`decrement_ref`](NativeRefCounted::decrement_ref) releases the
+/// object once the count reaches zero.
+///
+/// Releasing the object frees memory that `&self` still points to. This is only sound if every
+/// byte of the type is behind an [`UnsafeCell`](core::cell::UnsafeCell) (e.g. all fields are
+/// `Cell`s or atomics, with no padding between them), or if the type is an opaque, zero-sized
+/// stand-in for an object that native code frees. Otherwise `&self` asserts that the memory is
+/// immutable and stays valid for the whole call, and freeing it through any pointer is
+/// undefined behavior.
+///
+/// [`is_unique`](NativeRefCounted::is_unique) must only return `true` if no other reference to
+/// the object exists, since [`RCHandle::get_mut`] relies on it to hand out a mutable reference.
+///
+/// Types that don't have a reference count can implement both methods as no-ops, in which case
+/// dropping an [`RCHandle`] leaks the wrapped object.
+pub unsafe trait NativeRefCounted {
+    /// Increases the reference count of the object.
+    ///
+    /// # Safety
+    ///
+    /// Every call must eventually be balanced by a call to
+    /// [`decrement_ref`](NativeRefCounted::decrement_ref).
+    unsafe fn increment_ref(&self);
+
+    /// Decreases the reference count of the object, releasing it if the count reaches zero.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own one of the references to the object, and must not use the object
+    /// after giving that reference up.
+    unsafe fn decrement_ref(&self);
+
+    /// Returns the current reference count of the object.
+    fn ref_count(&self) -> usize;
+
+    /// Returns `true` if the caller holds the only reference to the object.
+    ///
+    /// Defaults to checking that the reference count is one, types with other kinds of
+    /// references (e.g. weak references) must account for them as well.
+    fn is_unique(&self) -> bool {
+        self.ref_count() == 1
+    }
+}
+
+/// A type that can be allocated natively.
+///
+/// # Safety
+///
+/// [`allocate`](NativeAllocate::allocate) must return either `null` or a pointer to a valid
+/// object that the caller owns a single reference to.
+pub unsafe trait NativeAllocate: Sized {
+    /// Allocates a new object and returns a pointer to it.
+    fn allocate() -> *mut Self;
+}
+
+/// A wrapper type represented by a reference counted pointer to the wrapped type.
+///
+/// Cloning the handle increments the reference count and dropping it decrements the count,
+/// see [`NativeRefCounted`].
+#[repr(transparent)]
+pub struct RCHandle<T: NativeRefCounted>(ptr::NonNull<T>);
+
+impl<T: NativeRefCounted> From<&RCHandle<T>> for RCHandle<T> {
+    fn from(rch: &RCHandle<T>) -> Self {
+        rch.clone()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: NativeRefCounted> From<Box<T>> for RCHandle<T> {
+    /// Takes ownership of the boxed object, does not increase the reference count.
+    fn from(b: Box<T>) -> Self {
+        // boxes are never null
+        Self(unsafe { ptr::NonNull::new_unchecked(Box::into_raw(b)) })
+    }
+}
+
+impl<T: NativeRefCounted> AsRef<RCHandle<T>> for RCHandle<T> {
+    fn as_ref(&self) -> &RCHandle<T> {
+        self
+    }
+}
+
+// Method call syntax resolves to the inherent `as_ref`/`as_mut`, these are for generic code.
+impl<T: NativeRefCounted> AsRef<T> for RCHandle<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        RCHandle::as_ref(self)
+    }
+}
+
+// Unguarded like `DerefMut`, prefer `get_mut` in new code.
+impl<T: NativeRefCounted> AsMut<T> for RCHandle<T> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut T {
+        unsafe { self.0.as_mut() }
+    }
+}
+
+impl<T: NativeRefCounted> RCHandle<T> {
+    /// Create a reference counted handle from a pointer.
+    ///
+    /// Takes ownership of the object the pointer points to, does not increase the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be `null` or point to a valid object that stays alive for as long as the
+    /// handle holds a reference to it. The caller gives one of its references up to the handle,
+    /// and dropping the handle calls [`decrement_ref`](NativeRefCounted::decrement_ref), which
+    /// may release the object. All pointer constructors of the handle types share this contract.
+    #[inline]
+    pub unsafe fn from_ptr(ptr: *mut T) -> Option<Self> {
+        ptr::NonNull::new(ptr).map(Self)
+    }
+
+    /// Create a reference counted handle from a pointer.
+    ///
+    /// Takes ownership of the object the pointer points to, does not increase the reference count.
+    ///
+    /// Returns [`NullPointerError`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// See [`from_ptr`](RCHandle::from_ptr).
+    #[inline]
+    pub unsafe fn try_from_ptr(ptr: *mut T) -> Result<Self, NullPointerError> {
+        Self::from_ptr(ptr).ok_or(NullPointerError)
+    }
+
+    /// Create a reference counted handle from a pointer that passes `check`.
+    ///
+    /// Takes ownership of the object the pointer points to, does not increase the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null` or rejected by `check`, in which case ownership
+    /// stays with the caller. `check` is only called on non-null pointers.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be `null` or valid for reads of `T`, since `check` receives a reference
+    /// to the object. `check` can only reject an object it can safely inspect, e.g. by a magic
+    /// value, not pointers to arbitrary memory.
+    #[inline]
+    pub unsafe fn from_ptr_checked(ptr: *mut T, check: impl FnOnce(&T) -> bool) -> Option<Self> {
+        let ptr = ptr::NonNull::new(ptr)?;
+        check(ptr.as_ref()).then(|| Self(ptr))
+    }
+
+    /// Create a reference counted handle from a pointer.
+    ///
+    /// Shares ownership with the object the pointer points to, therefore increases the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// See [`from_ptr`](RCHandle::from_ptr), except that the caller keeps its own reference.
+    #[inline]
+    pub unsafe fn from_shared_ptr(ptr: *mut T) -> Option<Self> {
+        ptr::NonNull::new(ptr).map(|ptr| {
+            ptr.as_ref().increment_ref();
+            Self(ptr)
+        })
+    }
+
+    /// Create a reference counted handle from a pointer.
+    ///
+    /// Shares ownership with the object the pointer points to, therefore increases the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// See [`from_shared_ptr`](RCHandle::from_shared_ptr).
+    #[inline]
+    #[deprecated(note = "use `from_shared_ptr` instead")]
+    pub unsafe fn from_unshared_ptr(ptr: *mut T) -> Option<Self> {
+        Self::from_shared_ptr(ptr)
+    }
+
+    /// Create a reference to the wrapper from a reference to a pointer that points to the wrapped type.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be `null` or point to a valid object that stays alive for as long as the
+    /// returned reference, see [`from_ptr`](RCHandle::from_ptr).
+    #[inline]
+    pub unsafe fn from_unshared_ptr_ref(t: &*mut T) -> &Option<Self> {
+        transmute_ref(t)
+    }
+
+    /// Create a reference counted handle from a mutable reference.
+    ///
+    /// Takes ownership of the referenced object, does not increase the reference count.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own one of the references to the object and give it up to the handle.
+    /// Dropping the handle calls [`decrement_ref`](NativeRefCounted::decrement_ref), so unless
+    /// another reference is kept alive, the object must not be used after the handle is dropped,
+    /// and it must be valid for [`decrement_ref`](NativeRefCounted::decrement_ref) to release it
+    /// (e.g. it must not live on the stack if releasing frees it).
+    #[inline]
+    pub unsafe fn from_ref(t: &mut T) -> Self {
+        // references cannot be null, so it's safe to call unwrap_unchecked() here
+        Self::from_ptr(t).unwrap_unchecked()
+    }
+
+    /// Returns the pointer to the handle.
+    ///
+    /// Use [`as_raw`](RCHandle::as_raw) to get a raw pointer that can be passed to native code.
+    #[inline]
+    pub fn as_ptr(&self) -> &ptr::NonNull<T> {
+        &self.0
+    }
+
+    /// Returns a raw pointer to the wrapped type, does not change the reference count.
+    ///
+    /// The handle keeps its reference, so the pointer is only valid for as long as the handle (or
+    /// another reference to the object) is alive. Use [`into_ptr`](RCHandle::into_ptr) to pass
+    /// ownership of the reference to native code instead.
+    #[inline]
+    pub fn as_raw(&self) -> *mut T {
+        self.0.as_ptr()
+    }
+
+    /// Returns a raw pointer to the wrapped type, same as [`as_raw`](RCHandle::as_raw).
+    #[inline]
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.as_raw()
+    }
+
+    /// Returns `true` if both handles point to the same object.
+    ///
+    /// Unlike `==`, which compares the wrapped values, this only compares addresses.
+    #[inline]
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        a.0 == b.0
+    }
+
+    /// Returns a reference to the wrapped type.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &T {
+        unsafe { self.0.as_ref() }
+    }
+
+    /// Returns a mutable reference to the wrapped type.
+    ///
+    /// The wrapped object may be shared with other handles, use [`get_mut`](RCHandle::get_mut) to
+    /// only get a mutable reference to a unique object.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    #[deprecated(note = "use `get_mut`, or `as_mut_unchecked` if no other reference is used")]
+    pub fn as_mut(&mut self) -> &mut T {
+        unsafe { self.0.as_mut() }
+    }
+
+    /// Returns a mutable reference to the wrapped type without checking that it is unique.
+    ///
+    /// # Safety
+    ///
+    /// No other reference to the wrapped object may be used while the returned reference is
+    /// alive, use [`get_mut`](RCHandle::get_mut) to check this at runtime instead.
+    #[inline]
+    pub unsafe fn as_mut_unchecked(&mut self) -> &mut T {
+        self.0.as_mut()
+    }
+
+    /// Returns a reference to a part of the wrapped type, selected by `f`.
+    ///
+    /// The returned reference borrows the handle, so it can't outlive it.
+    #[inline]
+    pub fn map_ref<U: ?Sized>(&self, f: impl FnOnce(&T) -> &U) -> &U {
+        f(self.as_ref())
+    }
+
+    /// Returns the number of references to the wrapped object.
+    ///
+    /// This is only a snapshot, other threads may change the reference count at any time.
+    #[inline]
+    pub fn ref_count(&self) -> usize {
+        self.as_ref().ref_count()
+    }
+
+    /// Returns `true` if this is the only reference to the wrapped object, see
+    /// [`NativeRefCounted::is_unique`].
+    #[inline]
+    pub fn is_unique(&self) -> bool {
+        NativeRefCounted::is_unique(self.as_ref())
+    }
+
+    /// Increases the reference count of the wrapped object without creating a new handle.
+    ///
+    /// # Safety
+    ///
+    /// The extra reference must be released exactly once, by the native side or with
+    /// [`decrement_strong_count`](RCHandle::decrement_strong_count).
+    #[inline]
+    pub unsafe fn increment_strong_count(&self) {
+        self.as_ref().increment_ref()
+    }
+
+    /// Decreases the reference count of the wrapped object without dropping a handle.
+    ///
+    /// # Safety
+    ///
+    /// Must only release a reference previously taken with
+    /// [`increment_strong_count`](RCHandle::increment_strong_count) or otherwise not owned by any
+    /// handle, so that this handle keeps its own reference.
+    #[inline]
+    pub unsafe fn decrement_strong_count(&self) {
+        self.as_ref().decrement_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped type if this is the only reference to it.
+    ///
+    /// Returns [`None`] if the wrapped object is shared.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if self.is_unique() {
+            Some(unsafe { self.0.as_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the wrapper and returns a pointer to the wrapped type.
+    ///
+    /// Does not decrease the reference count, the reference held by the handle is transferred to
+    /// the caller.
+    #[inline]
+    pub fn into_ptr(self) -> *mut T {
+        let ptr = self.0.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+
+    /// Consumes the wrapper and returns a reference to the wrapped type that lives for the rest of
+    /// the program.
+    ///
+    /// The reference held by the handle is leaked and never released, so the object stays alive.
+    #[inline]
+    pub fn leak<'a>(self) -> &'a T
+    where
+        T: 'a,
+    {
+        unsafe { &*self.into_ptr() }
+    }
+
+    /// Consumes the wrapper and returns the wrapped object as a [`Box`], does not decrease the
+    /// reference count.
+    ///
+    /// # Safety
+    ///
+    /// The object must have been allocated by a [`Box`] (for example, the handle was created from
+    /// one with [`From`]), and this must be the only reference to it.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub unsafe fn into_boxed(self) -> Box<T> {
+        Box::from_raw(self.into_ptr())
+    }
+
+    /// Consumes the wrapper and returns a pointer to the wrapped type if this is the only reference
+    /// to it, does not decrease the reference count.
+    ///
+    /// Otherwise, the handle is returned unchanged in an [`Err`].
+    #[inline]
+    pub fn try_unwrap(self) -> Result<*mut T, Self> {
+        if self.is_unique() {
+            Ok(self.into_ptr())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<T: NativeRefCounted + NativeAllocate> RCHandle<T> {
+    /// Allocate a new object and wrap it into a reference counted handle.
+    ///
+    /// Returns [`None`] if the allocation failed.
+    #[inline]
+    pub fn new() -> Option<Self> {
+        // `NativeAllocate` guarantees a null or valid, owned pointer
+        unsafe { Self::from_ptr(T::allocate()) }
+    }
+}
+
+impl<T: NativeRefCounted> Clone for RCHandle<T> {
+    fn clone(&self) -> Self {
+        unsafe { self.as_ref().increment_ref() }
+        Self(self.0)
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        // avoid incrementing and decrementing the same reference count
+        if !Self::ptr_eq(self, source) {
+            *self = source.clone();
+        }
+    }
+}
+
+impl<T: NativeRefCounted> Drop for RCHandle<T> {
+    fn drop(&mut self) {
+        unsafe { self.0.as_ref().decrement_ref() }
+    }
+}
+
+impl<T: NativeRefCounted + PartialEq> PartialEq for RCHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref().eq(other.as_ref())
+    }
+}
+
+impl<T: NativeRefCounted + Eq> Eq for RCHandle<T> {}
+
+// Hashes the wrapped value to stay consistent with `PartialEq`. Use `as_raw()` as the key to
+// hash by identity instead.
+impl<T: NativeRefCounted + Hash> Hash for RCHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
+impl<T: NativeRefCounted + fmt::Debug> fmt::Debug for RCHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RCHandle").field(self.as_ref()).finish()
+    }
+}
+
+impl<T: NativeRefCounted> fmt::Pointer for RCHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.0, f)
+    }
+}
+
+impl<T: NativeRefCounted> Deref for RCHandle<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+// Deprecated along with `RCHandle::as_mut` (trait impls can't carry `#[deprecated]`), and will
+// be removed in the next breaking release. Prefer `get_mut`.
+impl<T: NativeRefCounted> DerefMut for RCHandle<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.as_mut() }
+    }
+}
+
+// cloning and dropping a handle updates the reference count through `&T`, so the count must be
+// thread-safe for the handle to cross threads
+#[cfg(feature = "send")]
+unsafe impl<T: NativeAtomicRefCounted> Send for RCHandle<T> {}
+
+#[cfg(feature = "sync")]
+unsafe impl<T: NativeAtomicRefCounted> Sync for RCHandle<T> {}
+
+/// A reference counted type whose reference count is updated atomically.
+///
+/// # Safety
+///
+/// Implementors must guarantee that [`increment_ref`](NativeRefCounted::increment_ref) and
+/// [`decrement_ref`](NativeRefCounted::decrement_ref) can be called from multiple threads at the
+/// same time. Types that also implement [`NativeWeakRefCounted`] must guarantee the same for the
+/// weak reference methods.
+pub unsafe trait NativeAtomicRefCounted: NativeRefCounted {}
+
+/// A thread-safe wrapper type represented by a pointer to an atomically reference counted type.
+///
+/// Unlike [`RCHandle`], this is `Send`/`Sync` whenever the wrapped type is, regardless of the
+/// enabled features.
+#[repr(transparent)]
+pub struct ARCHandle<T: NativeAtomicRefCounted>(RCHandle<T>);
+
+impl<T: NativeAtomicRefCounted> ARCHandle<T> {
+    /// Create an atomically reference counted handle from a pointer.
+    ///
+    /// Takes ownership of the object the pointer points to, does not increase the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// See [`RCHandle::from_ptr`].
+    #[inline]
+    pub unsafe fn from_ptr(ptr: *mut T) -> Option<Self> {
+        RCHandle::from_ptr(ptr).map(Self)
+    }
+
+    /// Create an atomically reference counted handle from a pointer.
+    ///
+    /// Shares ownership with the object the pointer points to, therefore increases the reference count.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// See [`RCHandle::from_shared_ptr`].
+    #[inline]
+    pub unsafe fn from_shared_ptr(ptr: *mut T) -> Option<Self> {
+        RCHandle::from_shared_ptr(ptr).map(Self)
+    }
+
+    /// Returns a raw pointer to the wrapped type, does not change the reference count.
+    #[inline]
+    pub fn as_raw(&self) -> *mut T {
+        self.0.as_raw()
+    }
+
+    /// Returns `true` if both handles point to the same object.
+    #[inline]
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        RCHandle::ptr_eq(&a.0, &b.0)
+    }
+
+    /// Returns a reference to the wrapped type.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &T {
+        self.0.as_ref()
+    }
+
+    /// Returns the number of references to the wrapped object.
+    ///
+    /// This is only a snapshot, other threads may change the reference count at any time.
+    #[inline]
+    pub fn ref_count(&self) -> usize {
+        self.0.ref_count()
+    }
+
+    /// Consumes the wrapper and returns a pointer to the wrapped type.
+    ///
+    /// Does not decrease the reference count, the reference held by the handle is transferred to
+    /// the caller.
+    #[inline]
+    pub fn into_ptr(self) -> *mut T {
+        self.0.into_ptr()
+    }
+}
+
+impl<T: NativeAtomicRefCounted> Clone for ARCHandle<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0)
+    }
+}
+
+impl<T: NativeAtomicRefCounted + fmt::Debug> fmt::Debug for ARCHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ARCHandle").field(self.as_ref()).finish()
+    }
+}
+
+impl<T: NativeAtomicRefCounted> Deref for ARCHandle<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+unsafe impl<T: NativeAtomicRefCounted + Send + Sync> Send for ARCHandle<T> {}
+
+unsafe impl<T: NativeAtomicRefCounted + Send + Sync> Sync for ARCHandle<T> {}
+
+/// A reference counted type that also supports weak references.
+///
+/// # Safety
+///
+/// Implementors must guarantee that the memory of the object stays valid for as long as its weak
+/// reference count is above zero, even if the object itself has already been released.
+///
+/// [`is_unique`](NativeRefCounted::is_unique) must also return `false` while weak references
+/// exist, since they can be upgraded while a mutable reference from [`RCHandle::get_mut`] is
+/// alive.
+pub unsafe trait NativeWeakRefCounted: NativeRefCounted {
+    /// Increases the weak reference count of the object.
+    ///
+    /// # Safety
+    ///
+    /// Every call must eventually be balanced by a call to
+    /// [`decrement_weak_ref`](NativeWeakRefCounted::decrement_weak_ref).
+    unsafe fn increment_weak_ref(&self);
+
+    /// Decreases the weak reference count of the object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own one of the weak references to the object, and must not use the object
+    /// after giving that reference up.
+    unsafe fn decrement_weak_ref(&self);
+
+    /// Increases the reference count of the object if it hasn't been released yet.
+    ///
+    /// Returns `false` if the reference count has already reached zero.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own one of the weak references to the object.
+    unsafe fn try_increment_ref(&self) -> bool;
+}
+
+impl<T: NativeWeakRefCounted> RCHandle<T> {
+    /// Create a weak handle to the wrapped object, increases the weak reference count.
+    #[inline]
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        unsafe { self.as_ref().increment_weak_ref() }
+        WeakHandle(self.0)
+    }
+}
+
+/// A weak reference to an object managed by an [`RCHandle`].
+///
+/// A weak handle does not keep the object alive, use [`upgrade`](WeakHandle::upgrade) to access it.
+#[repr(transparent)]
+pub struct WeakHandle<T: NativeWeakRefCounted>(ptr::NonNull<T>);
+
+impl<T: NativeWeakRefCounted> WeakHandle<T> {
+    /// Attempts to create a reference counted handle to the object, increases the reference count.
+    ///
+    /// Returns [`None`] if the object has already been released.
+    #[inline]
+    pub fn upgrade(&self) -> Option<RCHandle<T>> {
+        unsafe { self.0.as_ref().try_increment_ref() }.then(|| RCHandle(self.0))
+    }
+}
+
+impl<T: NativeWeakRefCounted> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        unsafe { self.0.as_ref().increment_weak_ref() }
+        Self(self.0)
+    }
+}
+
+impl<T: NativeWeakRefCounted> Drop for WeakHandle<T> {
+    fn drop(&mut self) {
+        unsafe { self.0.as_ref().decrement_weak_ref() }
+    }
+}
+
+#[cfg(feature = "send")]
+unsafe impl<T: NativeWeakRefCounted + NativeAtomicRefCounted> Send for WeakHandle<T> {}
+
+#[cfg(feature = "sync")]
+unsafe impl<T: NativeWeakRefCounted + NativeAtomicRefCounted> Sync for WeakHandle<T> {}
+
+/// A type that needs to release native resources when dropped.
+pub trait NativeDrop {
+    /// Releases the object and its native resources.
+    ///
+    /// Takes a pointer rather than a reference, so that implementations may free the memory of
+    /// the object itself.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid object owned by the caller, which must not use the object after
+    /// this call.
+    unsafe fn drop(ptr: ptr::NonNull<Self>);
+}
+
+/// A wrapper type represented by an owning pointer to the wrapped type.
+///
+/// Dropping the handle releases the wrapped object, see [`NativeDrop`].
+#[repr(transparent)]
+pub struct RefHandle<T: NativeDrop>(ptr::NonNull<T>);
+
+impl<T: NativeDrop> RefHandle<T> {
+    /// Create a handle from a pointer.
+    ///
+    /// Takes ownership of the object the pointer points to.
+    ///
+    /// Returns [`None`] if the pointer is `null`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be `null` or point to a valid object owned by the caller. Dropping the
+    /// handle releases the object through [`NativeDrop::drop`].
+    #[inline]
+    pub unsafe fn from_ptr(ptr: *mut T) -> Option<Self> {
+        ptr::NonNull::new(ptr).map(Self)
+    }
+
+    /// Returns a reference to the wrapped type.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &T {
+        unsafe { self.0.as_ref() }
+    }
+
+    /// Returns a mutable reference to the wrapped type.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_mut(&mut self) -> &mut T {
+        unsafe { self.0.as_mut() }
+    }
+
+    /// Consumes the wrapper and returns a pointer to the wrapped type.
+    ///
+    /// The wrapped object is not released, ownership is transferred to the caller.
+    #[inline]
+    pub fn into_ptr(self) -> *mut T {
+        let ptr = self.0.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+}
+
+impl<T: NativeDrop> Drop for RefHandle<T> {
+    fn drop(&mut self) {
+        unsafe { <T as NativeDrop>::drop(self.0) }
+    }
+}
+
+impl<T: NativeDrop> Deref for RefHandle<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_ref()
+    }
+}
+
+impl<T: NativeDrop> DerefMut for RefHandle<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut()
+    }
+}
+
+#[cfg(feature = "send")]
+unsafe impl<T: NativeDrop> Send for RefHandle<T> {}
+
+#[cfg(feature = "sync")]
+unsafe impl<T: NativeDrop> Sync for RefHandle<T> {}
+
+/// Define a newtype wrapping a [`Handle`] to a native type.
+///
+/// The generated type can be constructed with `from_instance` or [`From`], converted back with
+/// `into_instance` or [`From`], and dereferences to the native type.
+///
+/// ```
+/// # use type_handle::handle;
+/// pub struct NativePoint {
+///     pub x: i32,
+///     pub y: i32,
+/// }
+///
+/// handle!(pub Point, NativePoint);
+///
+/// let mut point = Point::from_instance(NativePoint { x: 1, y: 2 });
+/// point.x += 2;
+/// assert!(point.instance().x == 3 && point.y == 2);
+///
+/// let native: NativePoint = point.into();
+/// assert!(native.x == 3);
+/// ```
+#[macro_export]
+macro_rules! handle {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $native:ty) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        $vis struct $name($crate::Handle<$native>);
+
+        impl $name {
+            /// Wrap a native instance into a handle.
+            #[inline]
+            #[must_use]
+            pub fn from_instance(native: $native) -> Self {
+                Self($crate::Handle::from_instance(native))
+            }
+
+            /// Consumes the wrapper and returns the native instance.
+            #[inline]
+            #[must_use]
+            pub fn into_instance(self) -> $native {
+                self.0.into_instance()
+            }
+
+            /// Returns a reference to the native instance.
+            #[inline]
+            #[must_use]
+            pub fn instance(&self) -> &$native {
+                self.0.instance()
+            }
+
+            /// Returns a mutable reference to the native instance.
+            #[inline]
+            #[must_use]
+            pub fn instance_mut(&mut self) -> &mut $native {
+                self.0.instance_mut()
+            }
+        }
+
+        impl ::core::convert::From<$native> for $name {
+            fn from(native: $native) -> Self {
+                Self::from_instance(native)
+            }
+        }
+
+        impl ::core::convert::From<$name> for $native {
+            fn from(handle: $name) -> Self {
+                handle.into_instance()
+            }
+        }
+
+        impl ::core::ops::Deref for $name {
+            type Target = $native;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                self.instance()
+            }
+        }
+
+        impl ::core::ops::DerefMut for $name {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                self.instance_mut()
+            }
+        }
+    };
+}
+
+/// Define a newtype wrapping an [`RCHandle`] to a native reference counted type.
+///
+/// The native type must implement [`NativeRefCounted`]. The generated type increments the
+/// reference count when cloned and decrements it when dropped.
+///
+/// ```
+/// # use type_handle::{rc_handle, NativeRefCounted};
+/// # use std::cell::Cell;
+/// pub struct NativeImage {
+///     pub width: u32,
+///     refs: Cell<usize>,
+/// }
+///
+/// unsafe impl NativeRefCounted for NativeImage {
+///     unsafe fn increment_ref(&self) {
+///         self.refs.set(self.refs.get() + 1);
+///     }
+///
+///     unsafe fn decrement_ref(&self) {
+///         self.refs.set(self.refs.get() - 1);
+///     }
+///
+///     fn ref_count(&self) -> usize {
+///         self.refs.get()
+///     }
+/// }
+///
+/// rc_handle!(pub Image, NativeImage);
+///
+/// let mut native = NativeImage { width: 64, refs: Cell::new(1) };
+/// let image = unsafe { Image::from_ptr(&mut native) }.unwrap();
+/// let image2 = image.clone();
+/// assert!(image2.width == 64 && image.ref_count() == 2);
+///
+/// drop((image, image2));
+/// assert!(native.refs.get() == 0);
+/// ```
+#[macro_export]
+macro_rules! rc_handle {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $native:ty) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        #[repr(transparent)]
+        $vis struct $name($crate::RCHandle<$native>);
+
+        impl $name {
+            /// Create a handle from a pointer, does not increase the reference count.
+            ///
+            /// Returns [`None`] if the pointer is `null`.
+            ///
+            /// # Safety
+            ///
+            /// Same as `RCHandle::from_ptr`.
+            #[inline]
+            pub unsafe fn from_ptr(ptr: *mut $native) -> ::core::option::Option<Self> {
+                $crate::RCHandle::from_ptr(ptr).map(Self)
+            }
+
+            /// Create a handle from a pointer, increases the reference count.
+            ///
+            /// Returns [`None`] if the pointer is `null`.
+            ///
+            /// # Safety
+            ///
+            /// Same as `RCHandle::from_shared_ptr`.
+            #[inline]
+            pub unsafe fn from_shared_ptr(ptr: *mut $native) -> ::core::option::Option<Self> {
+                $crate::RCHandle::from_shared_ptr(ptr).map(Self)
+            }
+
+            /// Consumes the wrapper and returns a pointer to the native object, does not decrease
+            /// the reference count.
+            #[inline]
+            pub fn into_ptr(self) -> *mut $native {
+                self.0.into_ptr()
+            }
+
+            /// Returns a reference to the native object.
+            #[inline]
+            pub fn as_ref(&self) -> &$native {
+                self.0.as_ref()
+            }
+        }
+
+        impl ::core::ops::Deref for $name {
+            type Target = $native;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                self.as_ref()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Thing {
+        number: i32,
+    }
+
+    // `Thing` isn't reference counted, so `RCHandle<Thing>` leaks on drop
+    unsafe impl NativeRefCounted for Thing {
+        unsafe fn increment_ref(&self) {}
+        unsafe fn decrement_ref(&self) {}
+
+        fn ref_count(&self) -> usize {
+            1
+        }
+    }
+
+    unsafe impl NativeAtomicRefCounted for Thing {}
+
+    // freed by `decrement_ref` while `&self` is borrowed, so every field must be a `Cell` (and
+    // there must be no padding)
+    struct Allocated {
+        refs: Cell<usize>,
+        value: Cell<usize>,
+    }
+
+    unsafe impl NativeAllocate for Allocated {
+        fn allocate() -> *mut Self {
+            Box::into_raw(Box::new(Self {
+                refs: Cell::new(1),
+                value: Cell::new(0),
+            }))
+        }
+    }
+
+    unsafe impl NativeRefCounted for Allocated {
+        unsafe fn increment_ref(&self) {
+            self.refs.set(self.refs.get() + 1);
+        }
+
+        unsafe fn decrement_ref(&self) {
+            self.refs.set(self.refs.get() - 1);
+            if self.refs.get() == 0 {
+                drop(Box::from_raw(self as *const Self as *mut Self));
+            }
+        }
+
+        fn ref_count(&self) -> usize {
+            self.refs.get()
+        }
+    }
+
+    #[derive(Debug)]
+    struct RefCounted {
+        refs: AtomicUsize,
+    }
+
+    impl RefCounted {
+        fn new() -> Self {
+            Self {
+                refs: AtomicUsize::new(1),
+            }
+        }
+
+        fn refs(&self) -> usize {
+            self.refs.load(Ordering::SeqCst)
+        }
+    }
+
+    unsafe impl NativeRefCounted for RefCounted {
+        unsafe fn increment_ref(&self) {
+            self.refs.fetch_add(1, Ordering::SeqCst);
+        }
+
+        unsafe fn decrement_ref(&self) {
+            self.refs.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn ref_count(&self) -> usize {
+            self.refs()
+        }
+    }
+
+    unsafe impl NativeAtomicRefCounted for RefCounted {}
+
+    struct WeakRefCounted {
+        refs: AtomicUsize,
+        weak_refs: AtomicUsize,
+    }
+
+    impl WeakRefCounted {
+        fn new() -> Self {
+            Self {
+                refs: AtomicUsize::new(1),
+                weak_refs: AtomicUsize::new(0),
+            }
+        }
+
+        fn weak_refs(&self) -> usize {
+            self.weak_refs.load(Ordering::SeqCst)
+        }
+    }
+
+    unsafe impl NativeRefCounted for WeakRefCounted {
+        unsafe fn increment_ref(&self) {
+            self.refs.fetch_add(1, Ordering::SeqCst);
+        }
+
+        unsafe fn decrement_ref(&self) {
+            self.refs.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn ref_count(&self) -> usize {
+            self.refs.load(Ordering::SeqCst)
+        }
+
+        fn is_unique(&self) -> bool {
+            self.ref_count() == 1 && self.weak_refs() == 0
+        }
+    }
+
+    unsafe impl NativeAtomicRefCounted for WeakRefCounted {}
+
+    unsafe impl NativeWeakRefCounted for WeakRefCounted {
+        unsafe fn increment_weak_ref(&self) {
+            self.weak_refs.fetch_add(1, Ordering::SeqCst);
+        }
+
+        unsafe fn decrement_weak_ref(&self) {
+            self.weak_refs.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        unsafe fn try_increment_ref(&self) -> bool {
+            self.refs
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |refs| {
+                    (refs != 0).then_some(refs + 1)
+                })
+                .is_ok()
+        }
+    }
+
+    struct Owned<'a> {
+        number: i32,
+        drops: &'a Cell<usize>,
+    }
+
+    impl NativeDrop for Owned<'_> {
+        unsafe fn drop(ptr: ptr::NonNull<Self>) {
+            let drops = ptr.as_ref().drops;
+            drops.set(drops.get() + 1);
+        }
+    }
+
+    struct Freed {
+        number: i32,
+    }
+
+    impl NativeDrop for Freed {
+        unsafe fn drop(ptr: ptr::NonNull<Self>) {
+            drop(Box::from_raw(ptr.as_ptr()));
+        }
+    }
+
+    #[cfg(feature = "send")]
+    #[test]
+    fn test_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Handle<*mut Thing>>();
+        assert_send::<RCHandle<Thing>>();
+        assert_send::<WeakHandle<WeakRefCounted>>();
+        assert_send::<RefHandle<Owned>>();
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<Handle<*mut Thing>>();
+        assert_sync::<RCHandle<Thing>>();
+        assert_sync::<WeakHandle<WeakRefCounted>>();
+        assert_sync::<RefHandle<Owned>>();
+    }
+
+    // ambiguous (and fails to compile) if the type implements Send
+    trait AmbiguousIfSend<A> {
+        fn check() {}
+    }
+    impl<T> AmbiguousIfSend<()> for T {}
+    impl<T: Send> AmbiguousIfSend<u8> for T {}
+
+    // ambiguous (and fails to compile) if the type implements Sync
+    trait AmbiguousIfSync<A> {
+        fn check() {}
+    }
+    impl<T> AmbiguousIfSync<()> for T {}
+    impl<T: Sync> AmbiguousIfSync<u8> for T {}
+
+    #[test]
+    fn test_rchandle_not_thread_safe() {
+        // `Allocated` counts references with a `Cell`
+        <RCHandle<Allocated> as AmbiguousIfSend<_>>::check();
+        <RCHandle<Allocated> as AmbiguousIfSync<_>>::check();
+    }
+
+    #[cfg(not(feature = "send"))]
+    #[test]
+    fn test_not_send() {
+        <Handle<std::rc::Rc<i32>> as AmbiguousIfSend<_>>::check();
+        <RCHandle<Thing> as AmbiguousIfSend<_>>::check();
+        <WeakHandle<WeakRefCounted> as AmbiguousIfSend<_>>::check();
+        <RefHandle<Owned> as AmbiguousIfSend<_>>::check();
+    }
+
+    #[cfg(not(feature = "sync"))]
+    #[test]
+    fn test_not_sync() {
+        <Handle<std::rc::Rc<i32>> as AmbiguousIfSync<_>>::check();
+        <RCHandle<Thing> as AmbiguousIfSync<_>>::check();
+        <WeakHandle<WeakRefCounted> as AmbiguousIfSync<_>>::check();
+        <RefHandle<Owned> as AmbiguousIfSync<_>>::check();
+    }
+
+    #[cfg(all(feature = "auto_traits", not(feature = "send"), not(feature = "sync")))]
+    #[test]
+    fn test_auto_traits() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Handle<i32>>();
+        <Handle<std::rc::Rc<i32>> as AmbiguousIfSend<_>>::check();
+    }
+
+    #[test]
+    fn test_handle() {
+        for num in 0..128 {
+            let thing = Thing { number: num };
+            let handle = Handle::from_instance(thing);
+            assert!(handle.number == num && handle.instance().number == num);
+        }
+    }
+
+    #[test]
+    fn test_handle_native() {
+        let mut handle = Handle::from_instance(Thing { number: 1 });
+        handle.native_mut().number = 2;
+        assert!(handle.native().number == 2 && ptr::eq(handle.native(), handle.instance()));
+    }
+
+    #[test]
+    fn test_handle_const() {
+        const HANDLE: Handle<u32> = Handle::from_instance(7);
+        assert!(*HANDLE.instance() == 7);
+    }
+
+    #[test]
+    fn test_handle_mut() {
+        for num in 0..128 {
+            let thing = Thing { number: num };
+            let mut handle = Handle::from_instance(thing);
+            assert!(
+                handle.number == num
+                    && handle.instance().number == num
+                    && handle.instance_mut().number == num
+            );
+            let new_num = num * 5;
+            handle.number = new_num;
+            assert!(handle.number == new_num);
+        }
+    }
+
+    #[test]
+    fn test_handle_layout() {
+        const _: () = {
+            assert!(mem::size_of::<Handle<u8>>() == mem::size_of::<u8>());
+            assert!(mem::size_of::<Handle<u64>>() == mem::size_of::<u64>());
+            assert!(mem::size_of::<Handle<Thing>>() == mem::size_of::<Thing>());
+            assert!(mem::size_of::<Handle<[u16; 3]>>() == mem::size_of::<[u16; 3]>());
+            assert!(mem::size_of::<Handle<()>>() == mem::size_of::<()>());
+        };
+    }
+
+    #[test]
+    fn test_handle_index() {
+        let mut handle = Handle::from_instance(vec![1, 2, 3]);
+        handle[1] = 5;
+        assert!(handle[1] == 5 && handle[1..] == [5, 3]);
+
+        let mut handle = Handle::from_instance([1, 2, 3, 4]);
+        handle[3] += 1;
+        assert!(handle[3] == 5 && handle[..2] == [1, 2]);
+    }
+
+    #[test]
+    fn test_handle_into_iter() {
+        let mut handle = Handle::from_instance(vec![1, 2, 3]);
+        for num in &mut handle {
+            *num *= 2;
+        }
+        assert!((&handle).into_iter().eq(&[2, 4, 6]));
+        assert!(handle.into_iter().sum::<i32>() == 12);
+    }
+
+    #[test]
+    fn test_handle_clone_from() {
+        let mut handle = Handle::from_instance(String::with_capacity(64));
+        let ptr = handle.as_ptr();
+        let buf = handle.as_str().as_ptr();
+
+        handle.clone_from(&Handle::from_instance(String::from("source")));
+        assert!(handle.instance() == "source");
+        assert!(ptr::eq(handle.as_ptr(), ptr) && ptr::eq(handle.as_str().as_ptr(), buf));
+    }
+
+    #[test]
+    fn test_handle_clone_native() {
+        struct Native {
+            data: Box<[u8]>,
+        }
+
+        impl NativeClone for Native {
+            fn clone(&self) -> Self {
+                Self {
+                    data: self.data.iter().copied().collect(),
+                }
+            }
+        }
+
+        let handle = Handle::from_instance(Native {
+            data: Box::new([1, 2, 3]),
+        });
+        let copy = handle.clone_native();
+        assert!(copy.data == handle.data && !ptr::eq(&*copy.data, &*handle.data));
+    }
+
+    #[test]
+    fn test_handle_eq_instance() {
+        let handle = Handle::from_instance(String::from("handle"));
+        assert!(handle == String::from("handle") && handle != String::from("other"));
+        assert_eq!(Handle::from_instance(5), 5);
+    }
+
+    #[test]
+    fn test_handle_eq_native() {
+        struct Native {
+            id: u32,
+            generation: u32,
+        }
+
+        impl NativePartialEq for Native {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+
+        let a = Handle::from_instance(Native {
+            id: 1,
+            generation: 1,
+        });
+        let b = Handle::from_instance(Native {
+            id: 1,
+            generation: 2,
+        });
+        let c = Handle::from_instance(Native {
+            id: 2,
+            generation: 1,
+        });
+        assert!(a.eq_native(&b) && !a.eq_native(&c));
+        assert!(a.generation != b.generation);
+    }
+
+    #[test]
+    fn test_handle_debug() {
+        assert!(format!("{:?}", Handle::from_instance(5)) == "Handle(5)");
+        assert!(format!("{:#?}", Handle::from_instance(5)) == "Handle(\n    5,\n)");
+    }
+
+    #[test]
+    fn test_handle_display() {
+        assert!(format!("{}", Handle::from_instance("handle")) == "handle");
+        assert!(format!("{:>5}", Handle::from_instance(42)) == format!("{:>5}", 42));
+        assert!(format!("{:.2}", Handle::from_instance(1.0)) == "1.00");
+    }
+
+    #[test]
+    fn test_handle_numeric_fmt() {
+        let handle = Handle::from_instance(255u32);
+        assert!(format!("{:#x}", handle) == "0xff");
+        assert!(format!("{:X}", handle) == "FF");
+        assert!(format!("{:#010b}", handle) == "0b11111111");
+        assert!(format!("{:o}", handle) == "377");
+    }
+
+    #[test]
+    fn test_handle_reborrow() {
+        fn read(handle: &Handle<i32>) -> i32 {
+            **handle
+        }
+
+        fn bump(handle: &mut Handle<i32>) {
+            **handle += 1;
+        }
+
+        let mut handle = Handle::from_instance(1);
+        let handle_mut = &mut handle;
+        bump(handle_mut.reborrow_mut());
+        assert!(read(handle_mut.reborrow()) == 2);
+        bump(handle_mut);
+        assert!(read(&handle) == 3);
+    }
+
+    #[test]
+    fn test_handle_pointer_fmt() {
+        let byte = 0u8;
+        let ptr: *const u8 = &byte;
+        let handle = Handle::from_instance(ptr);
+        assert!(format!("{:p}", handle) == format!("{:p}", ptr));
+    }
+
+    #[test]
+    fn test_handle_uninit() {
+        let handle = Handle::<Thing>::uninit().write(Thing { number: 4 });
+        assert!(handle.number == 4);
+
+        unsafe extern "C" fn native_init(out: *mut u32) {
+            out.write(42);
+        }
+
+        let mut handle = Handle::<u32>::uninit();
+        assert!(handle.as_write_ptr() == handle.as_mut_ptr().cast());
+        unsafe { native_init(handle.as_write_ptr()) };
+        let handle = unsafe { handle.assume_init() };
+        assert!(*handle == 42);
+    }
+
+    #[test]
+    fn test_handle_hash() {
+        use std::collections::hash_map::RandomState;
+        use std::hash::BuildHasher;
+
+        let state = RandomState::new();
+        for num in 0..128 {
+            let handle = Handle::from_instance(num);
+            assert!(state.hash_one(&handle) == state.hash_one(num));
+        }
+    }
+
+    #[test]
+    fn test_handle_eq() {
+        use std::collections::{HashMap, HashSet};
+
+        let set: HashSet<_> = [1, 2, 2, 3, 3, 3]
+            .into_iter()
+            .map(Handle::from_instance)
+            .collect();
+        assert!(set.len() == 3 && set.contains(&Handle::from_instance(2)));
+
+        let mut map = HashMap::new();
+        map.insert(Handle::from_instance("five"), 5);
+        assert!(map[&Handle::from_instance("five")] == 5);
+
+        fn assert_eq<T: Eq>() {}
+        assert_eq::<RCHandle<Thing>>();
+    }
+
+    #[test]
+    fn test_handle_ord() {
+        let mut nums = vec![5, -3, 12, 0, 7, -3];
+        let mut handles: Vec<_> = nums.iter().copied().map(Handle::from_instance).collect();
+        nums.sort();
+        handles.sort();
+        assert!(handles.into_iter().map(Handle::into_instance).eq(nums));
+    }
+
+    #[test]
+    fn test_handle_default() {
+        assert!(*Handle::<i32>::default().instance() == 0);
+        assert!(Handle::<String>::default().is_empty());
+    }
+
+    #[test]
+    fn test_handle_borrow() {
+        use std::collections::HashMap;
+
+        let mut map = HashMap::new();
+        map.insert(Handle::from_instance(String::from("one")), 1);
+        map.insert(Handle::from_instance(String::from("two")), 2);
+        assert!(map.get(&String::from("two")) == Some(&2));
+        assert!(!map.contains_key(&String::from("three")));
+
+        let mut handle = Handle::from_instance(5);
+        *BorrowMut::<i32>::borrow_mut(&mut handle) += 1;
+        assert!(*Borrow::<i32>::borrow(&handle) == 6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_handle_serde() {
+        let handle =
+            Handle::from_instance(vec![(1, String::from("one")), (2, String::from("two"))]);
+        let json = serde_json::to_string(&handle).unwrap();
+        assert!(json == serde_json::to_string(handle.instance()).unwrap());
+
+        let handle2: Handle<Vec<(i32, String)>> = serde_json::from_str(&json).unwrap();
+        assert!(handle2 == handle);
+    }
+
+    #[test]
+    fn test_handle_map() {
+        let handle = Handle::from_instance(42).map(|num| num.to_string());
+        assert!(handle.instance() == "42");
+    }
+
+    #[test]
+    fn test_handle_try_map() {
+        let handle = Handle::from_instance("42")
+            .try_map(str::parse::<i32>)
+            .unwrap();
+        assert!(*handle == 42);
+
+        let err = Handle::from_instance("forty two")
+            .try_map(str::parse::<i32>)
+            .unwrap_err();
+        assert!(err == "x".parse::<i32>().unwrap_err());
+    }
+
+    #[test]
+    fn test_handle_as_ref() {
+        fn len(s: impl AsRef<String>) -> usize {
+            s.as_ref().len()
+        }
+
+        fn push(mut s: impl AsMut<String>) -> String {
+            s.as_mut().push('!');
+            s.as_mut().clone()
+        }
+
+        let handle = Handle::from_instance(String::from("handle"));
+        assert!(len(&handle) == 6);
+        assert!(push(handle) == "handle!");
+    }
+
+    #[test]
+    fn test_handle_slice() {
+        let mut nums = [1, 2, 3, 4];
+        for handle in Handle::from_slice_mut(&mut nums) {
+            *handle.instance_mut() *= 10;
+        }
+        assert!(nums == [10, 20, 30, 40]);
+
+        let handles = Handle::from_slice(&nums);
+        assert!(handles.len() == 4 && *handles[2] == 30);
+        assert!(ptr::eq(Handle::as_slice(handles), &nums));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_handle_vec() {
+        let mut names = Vec::with_capacity(8);
+        names.extend(["a", "b", "c"].map(String::from));
+        let (ptr, len, capacity) = (names.as_ptr(), names.len(), names.capacity());
+
+        let handles = Handle::from_vec(names);
+        assert!(ptr::eq(handles.as_ptr() as *const String, ptr));
+        assert!(handles.len() == len && handles.capacity() == capacity);
+        assert!(handles[1].instance() == "b");
+
+        let names = Handle::into_vec(handles);
+        assert!(ptr::eq(names.as_ptr(), ptr));
+        assert!(names.len() == len && names.capacity() == capacity);
+        assert!(names == ["a", "b", "c"]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_handle_pin() {
+        let handle = Handle::pin(Thing { number: 8 });
+        let ptr = handle.as_ptr();
+        let thing = handle.as_ref().as_pin();
+        assert!(thing.number == 8 && ptr::eq(&*thing, ptr));
+
+        let mut handle = Handle::pin(core::marker::PhantomPinned);
+        let ptr = handle.as_ptr();
+        let pinned = handle.as_mut().as_pin_mut();
+        assert!(ptr::eq(&*pinned, ptr));
+    }
+
+    #[test]
+    fn test_handle_cast() {
+        #[repr(C)]
+        struct Color {
+            r: u8,
+            g: u8,
+            b: u8,
+            a: u8,
+        }
+
+        #[repr(C)]
+        struct Pixel {
+            channels: [u8; 4],
+        }
+
+        unsafe impl LayoutCompatible<Pixel> for Color {}
+        unsafe impl LayoutCompatible<Color> for Pixel {}
+
+        let mut handle = Handle::from_instance(Color {
+            r: 1,
+            g: 2,
+            b: 3,
+            a: 4,
+        });
+        let pixel: &Handle<Pixel> = handle.cast_ref();
+        assert!(pixel.channels == [handle.r, handle.g, handle.b, handle.a]);
+
+        handle.cast_mut::<Pixel>().channels[3] = 255;
+        assert!(handle.a == 255);
+    }
+
+    #[test]
+    fn test_handle_ptr() {
+        let mut handle = Handle::from_instance(Thing { number: 3 });
+        assert!(ptr::eq(handle.as_ptr(), handle.instance()));
+        assert!(ptr::eq(Handle::from_ptr(handle.as_ptr()), &handle));
+
+        let ptr = handle.as_mut_ptr();
+        unsafe { (*Handle::from_ptr_mut(ptr)).instance_mut().number = 4 };
+        assert!(handle.number == 4);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_handle_owned_ptr() {
+        let ptr = Handle::from_instance(vec![String::from("owned")]).into_ptr();
+        let handle = unsafe { Handle::from_owned_ptr(ptr) };
+        assert!(handle[0] == "owned");
+    }
+
+    #[test]
+    fn test_handle_replace_with() {
+        let mut handle = Handle::from_instance(String::from("hello"));
+        handle.replace_with(|s| s + ", world");
+        assert!(handle.instance() == "hello, world");
+    }
+
+    #[test]
+    fn test_handle_swap() {
+        struct Dropped<'a>(i32, &'a Cell<usize>);
+
+        impl Drop for Dropped<'_> {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mut a = Handle::from_instance(Dropped(1, &drops));
+        let mut b = Handle::from_instance(Dropped(2, &drops));
+        a.swap(&mut b);
+        assert!(a.instance().0 == 2 && b.instance().0 == 1 && drops.get() == 0);
+    }
+
+    #[test]
+    fn test_handle_take() {
+        let mut handle = Handle::from_instance(vec![1, 2, 3]);
+        assert!(handle.take() == [1, 2, 3]);
+        assert!(handle.is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_handle_leak() {
+        let leaked: &'static mut Vec<i32> = Handle::from_instance(vec![1]).leak();
+        leaked.push(2);
+        assert!(*leaked == [1, 2]);
+        // reclaim the allocation so that Miri doesn't report a leak
+        drop(unsafe { Box::from_raw(leaked) });
+    }
+
+    #[test]
+    fn test_handle_into_instance() {
+        let handle = Handle::from_instance(Box::new(5));
+        let boxed: Box<i32> = handle.into_instance();
+        assert!(*boxed == 5);
+
+        let mut num = 7;
+        let handle = Handle::from_instance((ptr::NonNull::from(&mut num), &num));
+        let (ptr, num_ref) = handle.into_instance();
+        assert!(ptr::eq(ptr.as_ptr(), num_ref));
+    }
+
+    #[allow(deprecated)]
+    #[test]
+    fn test_rchandle() {
+        for num in 0..128 {
+            let mut thing = Thing { number: num };
+            let mut rch = unsafe { RCHandle::from_ptr(&mut thing) }.unwrap();
             assert!(rch.number == num && rch.as_ref().number == num && rch.as_mut().number == num);
             let new_num = num * 6;
             rch.number = new_num;
             assert!(rch.number == new_num);
+            drop(rch);
 
-            let mut rch = RCHandle::from_ref(&mut thing);
+            let mut rch = unsafe { RCHandle::from_ref(&mut thing) };
             rch.number = 11;
             assert!(rch.number == 11);
         }
     }
+
+    #[test]
+    fn test_rchandle_guarded_mut() {
+        let mut obj = RefCounted::new();
+        let mut rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(rch.get_mut().is_some());
+
+        let rch2 = rch.clone();
+        assert!(rch.get_mut().is_none());
+        drop(rch2);
+        assert!(rch.get_mut().is_some());
+
+        let refs = unsafe { rch.as_mut_unchecked() }.refs.get_mut();
+        assert!(*refs == 1);
+    }
+
+    #[test]
+    fn test_rchandle_clone_increments_ref() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(rch.refs() == 1);
+
+        let rch2 = rch.clone();
+        assert!(rch.refs() == 2);
+
+        let rch3 = unsafe { RCHandle::from_shared_ptr(rch2.as_raw()) }.unwrap();
+        assert!(rch3.refs() == 3);
+    }
+
+    #[test]
+    fn test_rchandle_drop_decrements_ref() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        for _ in 0..8 {
+            let clones: Vec<_> = (0..4).map(|_| rch.clone()).collect();
+            assert!(rch.refs() == 5);
+            drop(clones);
+            assert!(rch.refs() == 1);
+        }
+        drop(rch);
+        assert!(obj.refs() == 0);
+    }
+
+    #[test]
+    fn test_rchandle_ref_count() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(rch.ref_count() == 1);
+
+        let rch2 = rch.clone();
+        let rch3 = rch2.clone();
+        assert!(rch.ref_count() == 3 && rch2.ref_count() == 3 && rch3.ref_count() == 3);
+
+        drop(rch2);
+        assert!(rch.ref_count() == 2);
+        drop(rch3);
+        assert!(rch.ref_count() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_strong_count() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        unsafe { rch.increment_strong_count() };
+        assert!(rch.refs() == 2);
+
+        // the native side takes over the extra reference and releases it later
+        let native = unsafe { RCHandle::from_ptr(rch.as_raw()) }
+            .unwrap()
+            .into_ptr();
+        unsafe { (*native).decrement_ref() };
+        assert!(rch.refs() == 1);
+
+        unsafe { rch.increment_strong_count() };
+        unsafe { rch.decrement_strong_count() };
+        assert!(rch.refs() == 1);
+        drop(rch);
+        assert!(obj.refs() == 0);
+    }
+
+    #[test]
+    fn test_archandle() {
+        let mut obj = RefCounted::new();
+        let arch = unsafe { ARCHandle::from_ptr(&mut obj) }.unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let arch = arch.clone();
+                scope.spawn(move || {
+                    let clones: Vec<_> = (0..64).map(|_| arch.clone()).collect();
+                    assert!(clones.iter().all(|clone| ARCHandle::ptr_eq(clone, &arch)));
+                });
+            }
+        });
+        assert!(arch.ref_count() == 1);
+
+        let shared = unsafe { ARCHandle::from_shared_ptr(arch.as_raw()) }.unwrap();
+        assert!(shared.refs() == 2 && format!("{:?}", shared).starts_with("ARCHandle("));
+        drop((arch, shared));
+        assert!(obj.refs() == 0);
+    }
+
+    #[test]
+    fn test_rchandle_get_mut() {
+        let mut obj = RefCounted::new();
+        let mut rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(rch.is_unique() && rch.get_mut().is_some());
+
+        let rch2 = rch.clone();
+        assert!(!rch.is_unique() && rch.get_mut().is_none());
+
+        drop(rch2);
+        assert!(rch.is_unique() && rch.get_mut().is_some());
+    }
+
+    #[test]
+    fn test_rchandle_try_unwrap() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = rch.clone();
+
+        let rch = rch.try_unwrap().unwrap_err();
+        assert!(rch.ref_count() == 2);
+        drop(rch2);
+
+        let ptr = rch.try_unwrap().ok().unwrap();
+        assert!(ptr::eq(ptr, &obj) && obj.refs() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_into_ptr_keeps_ref() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = rch.clone();
+        let ptr = rch2.into_ptr();
+        assert!(rch.ref_count() == 2);
+
+        drop(unsafe { RCHandle::from_ptr(ptr) }.unwrap());
+        assert!(rch.ref_count() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_ptr_eq() {
+        let mut obj = RefCounted::new();
+        let mut obj2 = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = unsafe { RCHandle::from_ref(&mut obj2) };
+        assert!(RCHandle::ptr_eq(&rch, &rch.clone()));
+        assert!(!RCHandle::ptr_eq(&rch, &rch2));
+    }
+
+    #[test]
+    fn test_weak_handle() {
+        let mut obj = WeakRefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let weak = rch.downgrade();
+        let weak2 = weak.clone();
+        assert!(rch.ref_count() == 1 && rch.weak_refs() == 2);
+
+        let upgraded = weak.upgrade().unwrap();
+        assert!(RCHandle::ptr_eq(&rch, &upgraded) && rch.ref_count() == 2);
+        drop(upgraded);
+        drop(rch);
+
+        assert!(weak.upgrade().is_none() && weak2.upgrade().is_none());
+        drop((weak, weak2));
+        assert!(obj.ref_count() == 0 && obj.weak_refs() == 0);
+    }
+
+    #[test]
+    fn test_weak_handle_get_mut() {
+        let mut obj = WeakRefCounted::new();
+        let mut rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(rch.get_mut().is_some());
+
+        // the weak handle could be upgraded while the mutable reference is alive
+        let weak = rch.downgrade();
+        assert!(!rch.is_unique() && rch.get_mut().is_none());
+        drop(weak);
+        assert!(rch.get_mut().is_some());
+    }
+
+    #[test]
+    fn test_rchandle_fmt() {
+        let mut obj = RefCounted::new();
+        let mut obj2 = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = unsafe { RCHandle::from_ref(&mut obj2) };
+        assert!(format!("{:?}", rch) == "RCHandle(RefCounted { refs: 1 })");
+        assert!(format!("{:p}", rch) == format!("{:p}", *rch.as_ptr()));
+        assert!(format!("{:p}", rch) == format!("{:p}", rch.clone()));
+        assert!(format!("{:p}", rch) != format!("{:p}", rch2));
+    }
+
+    #[test]
+    fn test_rchandle_as_raw() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        assert!(ptr::eq(rch.as_raw(), rch.as_ref()) && rch.ref_count() == 1);
+        assert!(ptr::eq(rch.as_mut_ptr(), rch.as_ref()) && rch.ref_count() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_new() {
+        let rch = RCHandle::<Allocated>::new().unwrap();
+        let rch2 = rch.clone();
+        assert!(rch.ref_count() == 2);
+        rch.value.set(3);
+        drop(rch);
+        assert!(rch2.ref_count() == 1 && rch2.value.get() == 3);
+    }
+
+    #[test]
+    fn test_rchandle_hash() {
+        use std::collections::HashSet;
+
+        let mut things = [
+            Thing { number: 1 },
+            Thing { number: 1 },
+            Thing { number: 2 },
+        ];
+        let set: HashSet<_> = things
+            .iter_mut()
+            .map(|thing| unsafe { RCHandle::from_ref(thing) })
+            .collect();
+        assert!(set.len() == 2);
+        assert!(set.contains(&unsafe { RCHandle::from_ref(&mut Thing { number: 2 }) }));
+    }
+
+    #[test]
+    fn test_rchandle_try_from_ptr() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::try_from_ptr(&mut obj) }.unwrap();
+        assert!(rch.ref_count() == 1);
+
+        let err = unsafe { RCHandle::<RefCounted>::try_from_ptr(ptr::null_mut()) }.unwrap_err();
+        assert!(err == NullPointerError && err.to_string() == "pointer is null");
+
+        let rch = unsafe { RCHandle::try_from_ptr(rch.into_ptr()) }.unwrap();
+        assert!(rch.ref_count() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_from_ptr_checked() {
+        let mut obj = RefCounted::new();
+        let mut calls = 0;
+        let check = |_: &RefCounted| {
+            calls += 1;
+            true
+        };
+        assert!(unsafe { RCHandle::from_ptr_checked(ptr::null_mut(), check) }.is_none());
+        assert!(calls == 0);
+
+        let rejected = unsafe { RCHandle::from_ptr_checked(&mut obj, |obj| obj.refs() == 2) };
+        assert!(rejected.is_none() && obj.refs() == 1);
+
+        let rch = unsafe { RCHandle::from_ptr_checked(&mut obj, |obj| obj.refs() == 1) }.unwrap();
+        assert!(rch.refs() == 1);
+        drop(rch);
+        assert!(obj.refs() == 0);
+    }
+
+    #[test]
+    fn test_refhandle() {
+        let drops = Cell::new(0);
+        let mut obj = Owned {
+            number: 1,
+            drops: &drops,
+        };
+
+        let mut rh = unsafe { RefHandle::from_ptr(&mut obj) }.unwrap();
+        rh.number = 2;
+        assert!(rh.as_ref().number == 2);
+        drop(rh);
+        assert!(drops.get() == 1);
+
+        let rh = unsafe { RefHandle::from_ptr(&mut obj) }.unwrap();
+        let ptr = rh.into_ptr();
+        assert!(ptr::eq(ptr, &obj) && drops.get() == 1);
+
+        assert!(unsafe { RefHandle::<Owned>::from_ptr(ptr::null_mut()) }.is_none());
+    }
+
+    #[test]
+    fn test_refhandle_frees() {
+        let ptr = Box::into_raw(Box::new(Freed { number: 1 }));
+        let mut rh = unsafe { RefHandle::from_ptr(ptr) }.unwrap();
+        rh.number += 1;
+        assert!(rh.number == 2);
+        // freed by `NativeDrop`, Miri reports a leak or double free otherwise
+        drop(rh);
+    }
+
+    #[test]
+    fn test_rchandle_leak() {
+        let mut obj = RefCounted::new();
+        let rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = rch.clone();
+        let leaked = rch2.leak();
+        drop(rch);
+        assert!(leaked.refs() == 1);
+    }
+
+    #[test]
+    fn test_rchandle_as_ref() {
+        fn number(thing: impl AsRef<Thing>) -> i32 {
+            thing.as_ref().number
+        }
+
+        let mut thing = Thing { number: 9 };
+        let mut rch = unsafe { RCHandle::from_ref(&mut thing) };
+        AsMut::<Thing>::as_mut(&mut rch).number += 1;
+        assert!(AsRef::<Thing>::as_ref(&rch).number == 10);
+        assert!(number(&rch) == 10);
+    }
+
+    #[test]
+    fn test_rchandle_map_ref() {
+        let mut thing = Thing { number: 12 };
+        let rch = unsafe { RCHandle::from_ref(&mut thing) };
+        assert!(*rch.map_ref(|thing| &thing.number) == 12);
+    }
+
+    #[test]
+    fn test_rchandle_clone_from() {
+        let mut obj = RefCounted::new();
+        let mut obj2 = RefCounted::new();
+        let mut rch = unsafe { RCHandle::from_ref(&mut obj) };
+        let rch2 = unsafe { RCHandle::from_ref(&mut obj2) };
+
+        rch.clone_from(&rch.clone());
+        assert!(rch.ref_count() == 1);
+
+        let rch3 = rch.clone();
+        rch.clone_from(&rch2);
+        assert!(RCHandle::ptr_eq(&rch, &rch2));
+        assert!(rch2.ref_count() == 2 && rch3.ref_count() == 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_rchandle_boxed() {
+        let rch = RCHandle::from(Box::new(Allocated {
+            refs: Cell::new(1),
+            value: Cell::new(5),
+        }));
+        let rch2 = rch.clone();
+        drop(rch);
+        let boxed = unsafe { rch2.into_boxed() };
+        assert!(boxed.refs.get() == 1 && boxed.value.get() == 5);
+
+        // dropping the last handle releases the box through `decrement_ref`
+        drop(RCHandle::from(boxed));
+    }
 }